@@ -0,0 +1,73 @@
+// config.js
+
+import { app } from "electron";
+import path from "path";
+import fs from "fs";
+import { defaults, migrate } from "./schema.js";
+
+const secretKey = /api[-_]?key|token|secret|password|authorization/i;
+
+let config = { ...defaults };
+
+export const getConfigPath = () => {
+  return path.join(app.getPath("userData"), "config.json");
+};
+
+export const getLogPath = () => {
+  return path.join(app.getPath("logs"), "worker.log");
+};
+
+export function loadConfig() {
+  const configPath = getConfigPath();
+
+  if (fs.existsSync(configPath)) {
+    try {
+      const saved = JSON.parse(fs.readFileSync(configPath, "utf-8"));
+      config = migrate(saved);
+
+      if (config.version !== saved.version) {
+        saveConfig({});
+      }
+    } catch (err) {
+      console.error(`Failed to read config, using defaults`, err);
+      config = { ...defaults };
+    }
+  }
+
+  return config;
+}
+
+export function getConfig() {
+  return config;
+}
+
+export function saveConfig(updates) {
+  const configPath = getConfigPath();
+  config = { ...config, ...updates };
+
+  fs.mkdirSync(path.dirname(configPath), { recursive: true });
+  fs.writeFileSync(configPath, JSON.stringify(config, null, 2));
+
+  return config;
+}
+
+function redact(value) {
+  if (Array.isArray(value)) {
+    return value.map(redact);
+  }
+
+  if (value && typeof value === "object") {
+    return Object.fromEntries(
+      Object.entries(value).map(([key, inner]) => [
+        key,
+        secretKey.test(key) && inner ? "***" : redact(inner),
+      ]),
+    );
+  }
+
+  return value;
+}
+
+export function exportRedactedConfig() {
+  return JSON.stringify(redact(config), null, 2);
+}
@@ -0,0 +1,30 @@
+// latency.js
+
+// rolling seconds of processing per second of audio over recent jobs, used
+// to estimate how long a recording will take before it's sent to the worker
+export function createLatencyTracker(size = 20, minSamples = 3) {
+  const samples = [];
+
+  return {
+    record(elapsed, audioSeconds) {
+      if (!(elapsed > 0 && audioSeconds > 0)) {
+        return;
+      }
+
+      samples.push(elapsed / audioSeconds);
+      if (samples.length > size) {
+        samples.shift();
+      }
+    },
+
+    // seconds, or "unknown" until there are enough samples to go on
+    estimate(durationSecs) {
+      if (samples.length < minSamples) {
+        return "unknown";
+      }
+
+      const total = samples.reduce((sum, sample) => sum + sample, 0);
+      return (total / samples.length) * durationSecs;
+    },
+  };
+}
@@ -0,0 +1,40 @@
+// latency.test.js
+
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { createLatencyTracker } from "./latency.js";
+
+test("is unknown until enough jobs have been timed", () => {
+  const latency = createLatencyTracker(20, 3);
+  latency.record(1, 10);
+  latency.record(1, 10);
+
+  assert.equal(latency.estimate(60), "unknown");
+});
+
+test("scales the average rate by the audio duration", () => {
+  const latency = createLatencyTracker(20, 3);
+  latency.record(1, 10);
+  latency.record(2, 10);
+  latency.record(3, 10);
+
+  assert.ok(Math.abs(latency.estimate(60) - 12) < 1e-9);
+});
+
+test("only averages the most recent jobs", () => {
+  const latency = createLatencyTracker(2, 1);
+  latency.record(10, 1);
+  latency.record(1, 1);
+  latency.record(3, 1);
+
+  assert.equal(latency.estimate(1), 2);
+});
+
+test("ignores failed or zero-length jobs", () => {
+  const latency = createLatencyTracker(20, 1);
+  latency.record(0, 0);
+  latency.record(1, 0);
+  latency.record(NaN, 5);
+
+  assert.equal(latency.estimate(10), "unknown");
+});
@@ -1,15 +1,53 @@
 // main.js
 
-import { app, BrowserWindow, Tray, ipcMain } from "electron";
+import {
+  app,
+  BrowserWindow,
+  Menu,
+  Tray,
+  clipboard,
+  globalShortcut,
+  ipcMain,
+  systemPreferences,
+} from "electron";
 import path from "path";
 import fs from "fs";
+import crypto from "crypto";
+import readline from "readline";
 import * as utils from "./utils.js";
+import * as config from "./config.js";
+import { postprocess } from "./postprocess.js";
+import { createLatencyTracker } from "./latency.js";
+import { logLevels, validateConfig } from "./schema.js";
+import {
+  formatSrt,
+  formatVtt,
+  parseTimestamps,
+  subtitleCues,
+  subtitleFormats,
+} from "./subtitles.js";
 
 let tray;
 let window;
 let worker;
 let workerStatus = utils.status.STOPPED;
-let saveRecording = false;
+let isRecording = false;
+let quitting = false;
+let workerRestarts = 0;
+let stopFollowingLog = null;
+
+const MAX_WORKER_RESTARTS = 3;
+const MAX_CACHED_SEGMENTS = 20;
+const CLEANUP_INTERVAL_MS = 60 * 60 * 1000;
+
+// transcription id -> recording file written by us, pending its outcome
+const pendingRecordings = new Map();
+// transcription id -> { filePath, resolve } for jobs the worker hasn't
+// answered yet
+const activeTranscriptions = new Map();
+// transcription id -> { text, segments, words } for subtitle export
+const segmentResults = new Map();
+const latency = createLatencyTracker();
 
 function createWindow() {
   window = new BrowserWindow({
@@ -21,7 +59,6 @@ function createWindow() {
       contextIsolation: true,
     },
   });
-  tray = new Tray(path.join(utils.getDirname(), "assets", "icon.png"));
 
   const startURL =
     process.env.ENV === "development"
@@ -31,45 +68,265 @@ function createWindow() {
   window.loadURL(startURL);
 
   window.on("closed", () => (window = null));
+}
+
+function showWindow() {
+  if (window === null) {
+    createWindow();
+  } else {
+    window.show();
+    window.focus();
+  }
+}
+
+function toggleRecording() {
+  // recording lives in the renderer, so it needs a window even from the tray
+  if (window === null) {
+    createWindow();
+    window.webContents.once("did-finish-load", () => {
+      window.webContents.send("toggle-recording");
+    });
+  } else {
+    window.webContents.send("toggle-recording");
+  }
+}
+
+function createTray() {
+  tray = new Tray(path.join(utils.getDirname(), "assets", "icon.png"));
+  tray.on("click", toggleRecording);
+  updateTray();
+}
+
+function updateTray() {
+  if (!tray) return;
+
+  let state = "Idle";
+  if (isRecording) {
+    state = "Recording";
+  } else if (activeTranscriptions.size > 0) {
+    state = "Transcribing";
+  }
+
+  tray.setToolTip(`Dictator - ${state}`);
+  tray.setContextMenu(
+    Menu.buildFromTemplate([
+      {
+        label: isRecording ? "Stop Recording" : "Start Recording",
+        click: toggleRecording,
+      },
+      { label: "Open", click: showWindow },
+      { type: "separator" },
+      { label: "Quit", click: () => app.quit() },
+    ]),
+  );
+}
+
+function registerHotkey(accelerator) {
+  globalShortcut.unregisterAll();
+  if (!accelerator) return true;
+
+  // register throws on malformed accelerators and returns false when
+  // another app already owns the shortcut
+  try {
+    return globalShortcut.register(accelerator, toggleRecording);
+  } catch {
+    return false;
+  }
+}
 
-  // TODO: implement tray behavior
+function finishTranscription(id, outcome) {
+  const job = activeTranscriptions.get(id);
+  activeTranscriptions.delete(id);
+  job?.resolve?.(outcome);
+  updateTray();
+}
+
+function failTranscription(id, reason) {
+  console.error(`Transcription ${id} failed: ${reason}`);
+  finishTranscription(id, { text: null, error: reason });
+  pendingRecordings.delete(id);
+  window?.webContents.send("transcription-failed", id, reason);
+}
+
+function waitForTranscription(id) {
+  return new Promise((resolve) => {
+    activeTranscriptions.get(id).resolve = resolve;
+  });
 }
 
 function createWorker() {
   worker = utils.spawnWorker();
+  workerStatus = utils.status.SETUP;
+
+  // read whole lines so multi-byte characters and messages split across
+  // chunks are decoded intact
+  const lines = readline.createInterface({ input: worker.stdout });
 
-  worker.stdout.on("data", (data) => {
-    const message = data.toString().trim();
+  lines.on("line", (line) => {
+    const message = line.trim();
 
     if (message == "[ready]") {
       console.log("Worker is ready!");
 
       workerStatus = utils.status.READY;
+      workerRestarts = 0;
       window?.webContents.send("worker-ready", workerStatus);
     }
 
-    if (message.startsWith("[transcription]")) {
-      const transcription = message.replace("[transcription]", "").trim();
-      window?.webContents.send("transcription", transcription);
+    if (message.startsWith("[error]")) {
+      const error = message.replace("[error]", "").trim();
+      console.error(`Worker error: ${error}`);
+      window?.webContents.send("worker-error", error);
+    }
+
+    if (message.startsWith("[timing]")) {
+      const [id, elapsed, seconds] = message
+        .replace("[timing]", "")
+        .trim()
+        .split(" ");
+      console.debug(`Transcription ${id} took ${elapsed}s for ${seconds}s`);
+      latency.record(Number(elapsed), Number(seconds));
+    }
+
+    if (message.startsWith("[transcript]")) {
+      const body = message.replace("[transcript]", "").trim();
+      const id = body.split(" ", 1)[0];
+      const raw = body.slice(id.length).trim();
+
+      // a bad rule or setting shouldn't lose the transcript or leave the
+      // job waiting forever
+      let transcription = raw;
+      try {
+        transcription = postprocess(raw, config.getConfig());
+      } catch (err) {
+        console.error(`Failed to post-process transcription ${id}`, err);
+      }
+      console.debug(`Transcription ${id}: "${raw}" -> "${transcription}"`);
+      finishTranscription(id, { text: transcription, error: null });
+      window?.webContents.send("transcription", id, transcription, raw);
+
+      // skip empty results so they don't wipe the user's clipboard
+      if (config.getConfig().autoCopy && transcription) {
+        try {
+          clipboard.writeText(transcription);
+        } catch (err) {
+          console.error(`Failed to copy transcription to clipboard`, err);
+        }
+      }
+
+      if (pendingRecordings.has(id)) {
+        utils.applyPostTranscriptionAction(pendingRecordings.get(id));
+        pendingRecordings.delete(id);
+      }
+    }
+
+    if (message.startsWith("[segments]")) {
+      const body = message.replace("[segments]", "").trim();
+      const id = body.split(" ", 1)[0];
+      const result = parseTimestamps(body.slice(id.length));
+      finishTranscription(id, { text: result.text, error: null });
+
+      segmentResults.set(id, result);
+      if (segmentResults.size > MAX_CACHED_SEGMENTS) {
+        segmentResults.delete(segmentResults.keys().next().value);
+      }
+      window?.webContents.send("transcription-segments", id, result);
+    }
+
+    // failed or empty recordings are kept so they can be retried
+    if (message.startsWith("[empty]")) {
+      const id = message.replace("[empty]", "").trim();
+      finishTranscription(id, { text: null, error: "No text recognized" });
+      pendingRecordings.delete(id);
+      window?.webContents.send("transcription-empty", id);
+    }
+
+    if (message.startsWith("[failed]")) {
+      const body = message.replace("[failed]", "").trim();
+      const id = body.split(" ", 1)[0];
+      failTranscription(id, body.slice(id.length).trim());
     }
   });
 
+  // writes racing a worker exit fail with EPIPE, the close handler
+  // already fails any jobs they carried
+  worker.stdin.on("error", (err) => {
+    console.error(`Failed to write to worker`, err);
+  });
+
   worker.stderr.on("data", (data) => {
     console.error(`Worker stderr: ${data}`);
   });
 
   worker.on("close", (code, signal) => {
     console.log(`Worker exited with code ${code} and signal ${signal}`);
+    workerStatus = utils.status.STOPPED;
+    pendingRecordings.clear();
+    for (const id of [...activeTranscriptions.keys()]) {
+      failTranscription(id, "Worker exited");
+    }
+
+    // bring the worker back after a crash, backing off between attempts
+    if (!quitting && workerRestarts < MAX_WORKER_RESTARTS) {
+      workerRestarts++;
+      console.log(`Restarting worker (attempt ${workerRestarts})`);
+      setTimeout(createWorker, 1000 * workerRestarts);
+    }
   });
 }
 
+function validateImport(filePath) {
+  if (workerStatus !== utils.status.READY) {
+    throw new Error("Worker is not ready");
+  }
+
+  const error = utils.validateAudioFile(filePath);
+  if (error) {
+    throw new Error(error);
+  }
+}
+
+function restartWorker() {
+  workerRestarts = 0;
+  if (workerStatus === utils.status.STOPPED) {
+    createWorker();
+  } else {
+    // the close handler spawns the replacement
+    worker.kill();
+  }
+}
+
+function transcribingPaths() {
+  return [...activeTranscriptions.values()].map((job) =>
+    path.resolve(job.filePath),
+  );
+}
+
+function checkNotTranscribing(filePath) {
+  // moving a file out from under the worker fails the job
+  const recordingPath = utils.resolveRecordingPath(filePath);
+  if (transcribingPaths().includes(recordingPath)) {
+    throw new Error(`Recording is still being transcribed: ${filePath}`);
+  }
+}
+
+function enforceQuota() {
+  // never delete a file the worker is still reading
+  utils.enforceRecordingsQuota(
+    config.getConfig().maxRecordingsMb,
+    transcribingPaths(),
+  );
+}
+
 function sendToWorker(audioBuffer) {
   if (workerStatus !== utils.status.READY) {
     // TODO send message to user
     console.error(`Worker is not ready`);
-    return;
+    return null;
   }
 
+  const id = crypto.randomUUID();
+
   utils.createTempAudioFile(audioBuffer, (err, filePath) => {
     if (err) {
       console.error(`Failed to create temp file`, err);
@@ -82,31 +339,302 @@ function sendToWorker(audioBuffer) {
       return;
     }
 
-    console.log(`Transcribing ${filePath}`);
-    worker.stdin.write(`\\transcribe ${filePath}\n`);
+    pendingRecordings.set(id, filePath);
+    transcribeFile(filePath, id);
+    enforceQuota();
   });
+
+  return id;
+}
+
+function transcribeFile(
+  filePath,
+  id = crypto.randomUUID(),
+  command = "transcribe",
+) {
+  activeTranscriptions.set(id, { filePath, resolve: null });
+
+  // the worker can exit while a recording is being written or during the
+  // restart delay, fail the job once callers have had a chance to wait on it
+  if (workerStatus === utils.status.STOPPED) {
+    queueMicrotask(() => failTranscription(id, "Worker is not running"));
+    return id;
+  }
+
+  console.log(`Transcribing ${filePath} (${id})`);
+  worker.stdin.write(`\\${command} ${id} ${filePath}\n`);
+  updateTray();
+  window?.webContents.send("transcription-started", id);
+  return id;
 }
 
 app.whenReady().then(() => {
+  config.loadConfig();
+
+  // read retention on every sweep so config changes apply without restart
+  const cleanup = () => {
+    utils.cleanupOldRecordings(config.getConfig().retentionDays);
+    enforceQuota();
+  };
+  cleanup();
+  setInterval(cleanup, CLEANUP_INTERVAL_MS);
+
   createWorker();
   createWindow();
+  createTray();
+
+  if (!registerHotkey(config.getConfig().hotkey)) {
+    console.warn(`Failed to register hotkey ${config.getConfig().hotkey}`);
+  }
 
   ipcMain.handle("check-worker", async () => {
     return workerStatus;
   });
 
+  ipcMain.handle("restart-worker", async () => {
+    restartWorker();
+  });
+
   ipcMain.handle("transcribe", async (event, audioBuffer) => {
-    sendToWorker(audioBuffer);
+    return sendToWorker(audioBuffer);
+  });
+
+  ipcMain.handle("transcribe-file", async (event, filePath) => {
+    validateImport(filePath);
+    return transcribeFile(filePath);
+  });
+
+  ipcMain.handle("transcribe-files", async (event, filePaths) => {
+    const results = [];
+
+    // one at a time, so a failure only affects its own entry
+    for (const [index, filePath] of filePaths.entries()) {
+      window?.webContents.send("batch-progress", {
+        index,
+        total: filePaths.length,
+        fileName: path.basename(filePath),
+      });
+
+      try {
+        validateImport(filePath);
+      } catch (err) {
+        results.push({
+          filePath,
+          success: false,
+          text: null,
+          error: err.message,
+        });
+        continue;
+      }
+
+      const { text, error } = await waitForTranscription(
+        transcribeFile(filePath),
+      );
+      results.push({ filePath, success: !error, text, error });
+    }
+
+    return results;
+  });
+
+  ipcMain.handle("list-recordings", async () => {
+    return utils.listRecordings();
+  });
+
+  ipcMain.handle("transcribe-recording", async (event, filePath) => {
+    // only allow recordings, so this can't be used to read arbitrary files
+    const recordingPath = utils.resolveRecordingPath(filePath);
+    validateImport(recordingPath);
+
+    const id = transcribeFile(recordingPath);
+    const { text, error } = await waitForTranscription(id);
+    return { id, success: !error, text, error };
+  });
+
+  ipcMain.handle("transcribe-segments", async (event, filePath) => {
+    validateImport(filePath);
+    return transcribeFile(filePath, undefined, "segments");
+  });
+
+  ipcMain.handle("transcribe-words", async (event, filePath) => {
+    validateImport(filePath);
+    return transcribeFile(filePath, undefined, "words");
+  });
+
+  ipcMain.handle("export-subtitles", async (event, id, format, outputPath) => {
+    if (!subtitleFormats.includes(format)) {
+      throw new Error(`Unknown subtitle format: ${format}`);
+    }
+
+    const result = segmentResults.get(id);
+    if (!result) {
+      throw new Error(`No segments for ${id}, run transcribe-segments first`);
+    }
+
+    const cues = subtitleCues(result);
+    if (!cues.length) {
+      throw new Error(`No timed text for ${id}, nothing to export`);
+    }
+
+    const filePath = path.extname(outputPath)
+      ? outputPath
+      : `${outputPath}.${format}`;
+    const formatter = format === "srt" ? formatSrt : formatVtt;
+
+    fs.writeFileSync(filePath, formatter(cues));
+    return filePath;
+  });
+
+  ipcMain.handle("inspect-audio-file", async (event, filePath) => {
+    return utils.inspectAudioFile(filePath);
+  });
+
+  ipcMain.handle("microphone-access", async () => {
+    // only macOS and Windows gate the microphone per app
+    if (!["darwin", "win32"].includes(process.platform)) {
+      return { status: "granted", message: null };
+    }
+
+    let status = systemPreferences.getMediaAccessStatus("microphone");
+    if (status === "not-determined" && process.platform === "darwin") {
+      const granted = await systemPreferences.askForMediaAccess("microphone");
+      status = granted ? "granted" : "denied";
+    }
+
+    const message =
+      status === "granted"
+        ? null
+        : "Grant microphone access to Dictator in your system settings";
+    return { status, message };
+  });
+
+  ipcMain.handle("is-recording", async () => {
+    return isRecording;
+  });
+
+  ipcMain.handle("move-recording", async (event, filePath, subfolder) => {
+    checkNotTranscribing(filePath);
+    return utils.moveRecording(filePath, subfolder);
+  });
+
+  ipcMain.handle("rename-recording", async (event, filePath, newName) => {
+    checkNotTranscribing(filePath);
+    return utils.renameRecording(filePath, newName);
+  });
+
+  ipcMain.on("recording-state", (event, recording) => {
+    isRecording = recording;
+    updateTray();
+  });
+
+  ipcMain.handle("get-config", async () => {
+    return config.getConfig();
+  });
+
+  ipcMain.handle("save-config", async (event, updates) => {
+    // collect every invalid field so a settings form can mark them all
+    const errors = validateConfig(updates);
+
+    if (updates.recordingsDir) {
+      const error = utils.ensureWritableDir(updates.recordingsDir);
+      if (error) {
+        errors.recordingsDir = error;
+      }
+    }
+
+    const action = updates.postTranscriptionAction;
+    if (action && !utils.postTranscriptionActions.includes(action)) {
+      errors.postTranscriptionAction = `Unknown action: ${action}`;
+    }
+
+    const previous = config.getConfig();
+    const hotkeyChanged =
+      "hotkey" in updates && updates.hotkey !== previous.hotkey;
+
+    if (hotkeyChanged && !registerHotkey(updates.hotkey)) {
+      errors.hotkey = `Invalid or unavailable hotkey: ${updates.hotkey}`;
+    }
+
+    // only the message crosses IPC, so the map travels as JSON
+    if (Object.keys(errors).length) {
+      if (hotkeyChanged) {
+        registerHotkey(previous.hotkey);
+      }
+      throw new Error(JSON.stringify(errors));
+    }
+
+    const saved = config.saveConfig(updates);
+
+    if (utils.workerConfigKeys.some((key) => previous[key] !== saved[key])) {
+      restartWorker();
+    }
+
+    return saved;
+  });
+
+  ipcMain.handle("export-config-redacted", async () => {
+    return config.exportRedactedConfig();
+  });
+
+  ipcMain.handle("check-storage", async () => {
+    return utils.checkStorage();
+  });
+
+  ipcMain.handle("estimate-transcription-time", async (event, durationSecs) => {
+    if (!Number.isFinite(durationSecs) || durationSecs < 0) {
+      throw new Error(`Invalid audio duration: ${durationSecs}`);
+    }
+
+    return latency.estimate(durationSecs);
+  });
+
+  ipcMain.handle("tail-log", async (event, lines = 200) => {
+    if (!Number.isInteger(lines) || lines <= 0) {
+      throw new Error(`Line count must be a positive integer: ${lines}`);
+    }
+
+    return utils.tailFile(config.getLogPath(), lines);
+  });
+
+  ipcMain.handle("follow-log", async (event, follow) => {
+    stopFollowingLog?.();
+    stopFollowingLog = null;
+
+    if (follow) {
+      stopFollowingLog = utils.followFile(config.getLogPath(), (lines) => {
+        window?.webContents.send("log-lines", lines);
+      });
+    }
+  });
+
+  ipcMain.handle("get-log-level", async () => {
+    return config.getConfig().logLevel;
+  });
+
+  ipcMain.handle("set-log-level", async (event, level) => {
+    level = typeof level === "string" ? level.toUpperCase() : level;
+    if (!logLevels.includes(level)) {
+      throw new Error(`Unknown log level: ${level}`);
+    }
+
+    config.saveConfig({ logLevel: level });
+
+    // a stopped worker picks up the saved level when it respawns
+    if (workerStatus !== utils.status.STOPPED) {
+      worker.stdin.write(`\\loglevel ${level}\n`);
+    }
+    return level;
   });
 
   window?.webContents.send("worker-ready", workerStatus);
 });
 
 app.on("window-all-closed", () => {
-  if (process.platform !== "darwin") {
-    app.quit();
-    if (!saveRecording) utils.deleteAllRecordings();
-  }
+  // keep running in the tray, quitting goes through the tray menu
+});
+
+app.on("will-quit", () => {
+  quitting = true;
+  globalShortcut.unregisterAll();
 });
 
 app.on("activate", () => {
@@ -0,0 +1,259 @@
+// postprocess.js
+
+// phrases whisper tends to hallucinate on trailing silence
+export const defaultHallucinationPatterns = [
+  "thank you\\.?",
+  "thanks for watching[.!]?",
+  "please subscribe[.!]?",
+  "subtitles by .*",
+];
+
+// CJK sentence punctuation isn't followed by a space
+const sentenceStart = "(?:^|(?<=[.!?])\\s+|(?<=[。！？])\\s*)";
+
+function compileTailPatterns(patterns) {
+  const compiled = [];
+
+  // only match a standalone trailing sentence, so speech that merely ends
+  // with the phrase ("I wanted to say thank you.") is left alone
+  for (const pattern of patterns) {
+    try {
+      compiled.push(new RegExp(`${sentenceStart}(?:${pattern})\\s*$`, "i"));
+    } catch {
+      console.warn(`Ignoring invalid hallucination pattern: ${pattern}`);
+    }
+  }
+
+  return compiled;
+}
+
+export function stripHallucinations(text, patterns) {
+  const compiled = compileTailPatterns(patterns);
+  let result = text.trim();
+  let stripped = true;
+
+  // repeat so stacked artifacts ("Thank you. Thanks for watching!") all go
+  while (stripped && result) {
+    stripped = false;
+    for (const re of compiled) {
+      if (re.test(result)) {
+        result = result.replace(re, "").trimEnd();
+        stripped = true;
+      }
+    }
+  }
+
+  return result;
+}
+
+// letters and digits count as part of a word, except in scripts written
+// without spaces, so "colonel" still matches inside "我用colonel系统"
+const wordChar =
+  "(?![\\p{sc=Han}\\p{sc=Hiragana}\\p{sc=Katakana}])[\\p{L}\\p{N}_]";
+
+function escapeRegExp(text) {
+  return text.replace(/[.*+?^${}()|[\]\\]/g, "\\$&");
+}
+
+export function applyReplacements(text, replacements) {
+  let result = text;
+
+  for (const { from, to, regex } of replacements) {
+    if (!from) continue;
+
+    // plain rules match whole words regardless of case
+    let pattern;
+    try {
+      pattern = regex
+        ? new RegExp(from, "g")
+        : new RegExp(
+            `(?<!${wordChar})${escapeRegExp(from)}(?!${wordChar})`,
+            "giu",
+          );
+    } catch {
+      console.warn(`Ignoring invalid replacement pattern: ${from}`);
+      continue;
+    }
+
+    // plain replacement text shouldn't have $ patterns expanded
+    result = result.replace(pattern, regex ? to : () => to);
+  }
+
+  return result;
+}
+
+const units = {
+  zero: 0,
+  one: 1,
+  two: 2,
+  three: 3,
+  four: 4,
+  five: 5,
+  six: 6,
+  seven: 7,
+  eight: 8,
+  nine: 9,
+  ten: 10,
+  eleven: 11,
+  twelve: 12,
+  thirteen: 13,
+  fourteen: 14,
+  fifteen: 15,
+  sixteen: 16,
+  seventeen: 17,
+  eighteen: 18,
+  nineteen: 19,
+};
+const tens = {
+  twenty: 20,
+  thirty: 30,
+  forty: 40,
+  fifty: 50,
+  sixty: 60,
+  seventy: 70,
+  eighty: 80,
+  ninety: 90,
+};
+const scales = { hundred: 100, thousand: 1000, million: 1e6, billion: 1e9 };
+
+const numberWord = Object.keys({ ...units, ...tens, ...scales }).join("|");
+const numberRun = new RegExp(
+  `\\b(?:${numberWord})(?:[\\s-]+(?:and[\\s-]+)?(?:${numberWord}))*\\b`,
+  "gi",
+);
+
+function parseSmall(words) {
+  // split into values below 100, e.g. ["twenty", "four"] -> [24]
+  const groups = [];
+
+  for (const word of words) {
+    const last = groups[groups.length - 1];
+    if (word in tens) {
+      groups.push(tens[word]);
+    } else if (units[word] < 10 && last >= 20 && last % 10 === 0) {
+      groups[groups.length - 1] += units[word];
+    } else {
+      groups.push(units[word]);
+    }
+  }
+
+  return groups;
+}
+
+function parseScaled(words) {
+  let total = 0;
+  let current = 0;
+  let lastScale = Infinity;
+
+  for (const word of words) {
+    if (word in scales) {
+      const scale = scales[word];
+
+      // reject repeated or rising scales, e.g. "one hundred two hundred"
+      // or "one thousand two million"
+      if (scale === 100) {
+        if (current >= 100) return null;
+        current = (current || 1) * 100;
+      } else {
+        if (scale >= lastScale) return null;
+        total += (current || 1) * scale;
+        current = 0;
+        lastScale = scale;
+      }
+      continue;
+    }
+
+    const value = word in tens ? tens[word] : units[word];
+    const below100 = current % 100;
+    const fits =
+      value >= 10
+        ? below100 === 0
+        : below100 === 0 || (below100 >= 20 && below100 % 10 === 0);
+
+    // reject sequences like "twenty twenty" or "five four" around scales
+    if (!fits) {
+      return null;
+    }
+    current += value;
+  }
+
+  return total + current;
+}
+
+export function parseNumberWords(phrase) {
+  const words = phrase
+    .toLowerCase()
+    .split(/[\s-]+/)
+    .filter((word) => word !== "and");
+
+  // single words ("one of them") are left alone to avoid mangling prose
+  if (words.length < 2) {
+    return null;
+  }
+
+  if (words.some((word) => word in scales)) {
+    return parseScaled(words);
+  }
+
+  const groups = parseSmall(words);
+  if (groups.length === 1) {
+    return groups[0];
+  }
+
+  // year style, e.g. "nineteen ninety nine" or "twenty twenty-four", kept
+  // to 19xx and 20xx so times like "eleven thirty" are left alone
+  if (groups.length === 2 && [19, 20].includes(groups[0]) && groups[1] >= 10) {
+    return groups[0] * 100 + groups[1];
+  }
+
+  return null;
+}
+
+export function formatNumbers(text, locale) {
+  return text.replace(numberRun, (phrase) => {
+    const value = parseNumberWords(phrase);
+    if (value === null) {
+      return phrase;
+    }
+
+    return new Intl.NumberFormat(locale, {
+      useGrouping: value >= 10000,
+    }).format(value);
+  });
+}
+
+export function formatTimestamp(date, format) {
+  const pad = (n) => n.toString().padStart(2, "0");
+  const tokens = {
+    YYYY: date.getFullYear().toString(),
+    MM: pad(date.getMonth() + 1),
+    DD: pad(date.getDate()),
+    HH: pad(date.getHours()),
+    mm: pad(date.getMinutes()),
+    ss: pad(date.getSeconds()),
+  };
+
+  return format.replace(/YYYY|MM|DD|HH|mm|ss/g, (token) => tokens[token]);
+}
+
+export function postprocess(text, config) {
+  let result = text;
+
+  if (config.stripHallucinations) {
+    result = stripHallucinations(result, config.hallucinationPatterns);
+  }
+
+  if (config.replacements?.length) {
+    result = applyReplacements(result, config.replacements);
+  }
+
+  if (config.numberFormatting) {
+    result = formatNumbers(result, config.locale);
+  }
+
+  if (config.prependTimestamp && result) {
+    result = `${formatTimestamp(new Date(), config.timestampFormat)} ${result}`;
+  }
+
+  return result;
+}
@@ -0,0 +1,176 @@
+// postprocess.test.js
+
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import {
+  applyReplacements,
+  defaultHallucinationPatterns,
+  formatNumbers,
+  postprocess,
+  stripHallucinations,
+} from "./postprocess.js";
+
+test("replaces plain rules as whole words regardless of case", () => {
+  const rules = [{ from: "colonel", to: "kernel" }];
+
+  assert.equal(
+    applyReplacements("Colonel COLONEL colonels", rules),
+    "kernel kernel colonels",
+  );
+});
+
+test("applies overlapping rules in order on the running result", () => {
+  const rules = [
+    { from: "colonel", to: "kernel" },
+    { from: "kernel panic", to: "KP" },
+  ];
+
+  assert.equal(applyReplacements("a colonel panic", rules), "a KP");
+  assert.equal(
+    applyReplacements("a colonel panic", [...rules].reverse()),
+    "a kernel panic",
+  );
+});
+
+test("lets an earlier rule consume text a later one would match", () => {
+  const rules = [
+    { from: "new york", to: "NYC" },
+    { from: "new", to: "old" },
+  ];
+
+  assert.equal(applyReplacements("new york is new", rules), "NYC is old");
+});
+
+test("expands capture groups in regex rules", () => {
+  const rules = [
+    { from: "v(\\d)\\.(\\d)", to: "version $1 point $2", regex: true },
+  ];
+
+  assert.equal(
+    applyReplacements("v1.2 and v3.4", rules),
+    "version 1 point 2 and version 3 point 4",
+  );
+});
+
+test("keeps $ patterns literal in plain rules", () => {
+  const rules = [{ from: "c++", to: "$1 C++" }];
+
+  assert.equal(applyReplacements("c++ rocks", rules), "$1 C++ rocks");
+});
+
+test("skips invalid regex rules and keeps going", () => {
+  const rules = [
+    { from: "(", to: "x", regex: true },
+    { from: "teh", to: "the" },
+  ];
+
+  assert.equal(applyReplacements("teh end", rules), "the end");
+});
+
+const strip = (text) => stripHallucinations(text, defaultHallucinationPatterns);
+
+test("strips a standalone trailing hallucinated sentence", () => {
+  assert.equal(strip("Hello there. Thank you."), "Hello there.");
+  assert.equal(strip("Thank you."), "");
+});
+
+test("strips stacked hallucinations", () => {
+  assert.equal(strip("Done. Thank you. Thanks for watching!"), "Done.");
+});
+
+test("keeps speech that ends with a hallucination phrase", () => {
+  assert.equal(
+    strip("I just wanted to say thank you."),
+    "I just wanted to say thank you.",
+  );
+  assert.equal(
+    strip("We said thanks for watching"),
+    "We said thanks for watching",
+  );
+});
+
+const numbers = (text) => formatNumbers(text, "en-US");
+
+test("converts spoken numbers and years to digits", () => {
+  assert.equal(numbers("twenty four hours"), "24 hours");
+  assert.equal(numbers("in twenty twenty-four"), "in 2024");
+  assert.equal(numbers("nineteen ninety nine"), "1999");
+  assert.equal(numbers("one hundred and one"), "101");
+  assert.equal(numbers("two thousand three hundred and five"), "2305");
+  assert.equal(numbers("five hundred thousand"), "500,000");
+});
+
+test("groups digits by locale", () => {
+  assert.equal(formatNumbers("five hundred thousand", "de-DE"), "500.000");
+});
+
+test("leaves times and ambiguous runs alone", () => {
+  for (const text of [
+    "meet at eleven thirty",
+    "the ten twenty train",
+    "twelve fifteen pm",
+    "twenty one twenty two",
+    "one hundred two hundred",
+    "one thousand two million",
+    "one of them",
+  ]) {
+    assert.equal(numbers(text), text);
+  }
+});
+
+test("matches plain rules next to CJK text", () => {
+  const rules = [
+    { from: "colonel", to: "kernel" },
+    { from: "东京", to: "Tokyo" },
+  ];
+
+  assert.equal(applyReplacements("我用colonel系统", rules), "我用kernel系统");
+  assert.equal(applyReplacements("我在东京工作", rules), "我在Tokyo工作");
+});
+
+test("treats accented letters as part of a word", () => {
+  const rules = [{ from: "caf", to: "X" }];
+
+  assert.equal(applyReplacements("café caf", rules), "café X");
+});
+
+test("replaces with and next to emoji", () => {
+  const rules = [
+    { from: "thumbs up", to: "👍" },
+    { from: "colonel", to: "kernel" },
+  ];
+
+  assert.equal(
+    applyReplacements("great thumbs up 🎉colonel", rules),
+    "great 👍 🎉kernel",
+  );
+});
+
+test("strips hallucinations after CJK punctuation and emoji", () => {
+  assert.equal(strip("你好。Thank you."), "你好。");
+  assert.equal(strip("Great 🎉. Thank you."), "Great 🎉.");
+});
+
+test("converts spoken numbers next to CJK text", () => {
+  assert.equal(numbers("我有twenty four个苹果 🍎"), "我有24个苹果 🍎");
+});
+
+test("runs CJK and emoji transcripts through the whole pipeline", () => {
+  const config = {
+    stripHallucinations: true,
+    hallucinationPatterns: defaultHallucinationPatterns,
+    replacements: [{ from: "colonel", to: "kernel" }],
+    numberFormatting: true,
+    locale: "en-US",
+    prependTimestamp: false,
+  };
+
+  assert.equal(
+    postprocess("我有twenty four个colonel 🎉。Thank you.", config),
+    "我有24个kernel 🎉。",
+  );
+  assert.equal(
+    postprocess("日本語のテキスト 👋", config),
+    "日本語のテキスト 👋",
+  );
+});
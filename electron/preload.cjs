@@ -5,16 +5,117 @@ const { contextBridge, ipcRenderer } = require("electron");
 
 contextBridge.exposeInMainWorld("nodeAPI", {
   sendTranscribeRequest: (audioBuffer) => {
-    ipcRenderer.invoke("transcribe", audioBuffer);
+    return ipcRenderer.invoke("transcribe", audioBuffer);
+  },
+  transcribeFile: (filePath) => {
+    return ipcRenderer.invoke("transcribe-file", filePath);
+  },
+  transcribeFiles: (filePaths) => {
+    return ipcRenderer.invoke("transcribe-files", filePaths);
+  },
+  onBatchProgress: (callback) => {
+    ipcRenderer.on("batch-progress", callback);
+  },
+  listRecordings: () => {
+    return ipcRenderer.invoke("list-recordings");
+  },
+  transcribeRecording: (filePath) => {
+    return ipcRenderer.invoke("transcribe-recording", filePath);
+  },
+  transcribeSegments: (filePath) => {
+    return ipcRenderer.invoke("transcribe-segments", filePath);
+  },
+  transcribeWords: (filePath) => {
+    return ipcRenderer.invoke("transcribe-words", filePath);
+  },
+  estimateTranscriptionTime: (durationSecs) => {
+    return ipcRenderer.invoke("estimate-transcription-time", durationSecs);
+  },
+  exportSubtitles: (id, format, outputPath) => {
+    return ipcRenderer.invoke("export-subtitles", id, format, outputPath);
+  },
+  inspectAudioFile: (filePath) => {
+    return ipcRenderer.invoke("inspect-audio-file", filePath);
+  },
+  moveRecording: (filePath, subfolder) => {
+    return ipcRenderer.invoke("move-recording", filePath, subfolder);
+  },
+  renameRecording: (filePath, newName) => {
+    return ipcRenderer.invoke("rename-recording", filePath, newName);
+  },
+  getConfig: () => {
+    return ipcRenderer.invoke("get-config");
+  },
+  // rejects with an Error whose message is a JSON field -> message map
+  saveConfig: async (updates) => {
+    try {
+      return await ipcRenderer.invoke("save-config", updates);
+    } catch (err) {
+      // drop the "Error invoking remote method" prefix IPC adds
+      const start = err.message.indexOf("{");
+      throw start === -1 ? err : new Error(err.message.slice(start));
+    }
+  },
+  exportConfigRedacted: () => {
+    return ipcRenderer.invoke("export-config-redacted");
+  },
+  checkStorage: () => {
+    return ipcRenderer.invoke("check-storage");
+  },
+  tailLog: (lines) => {
+    return ipcRenderer.invoke("tail-log", lines);
+  },
+  followLog: (follow) => {
+    return ipcRenderer.invoke("follow-log", follow);
+  },
+  onLogLines: (callback) => {
+    ipcRenderer.on("log-lines", callback);
+  },
+  getLogLevel: () => {
+    return ipcRenderer.invoke("get-log-level");
+  },
+  setLogLevel: (level) => {
+    return ipcRenderer.invoke("set-log-level", level);
   },
   checkWorker: () => {
     ipcRenderer.invoke("check-worker");
   },
+  restartWorker: () => {
+    return ipcRenderer.invoke("restart-worker");
+  },
   onTranscription: (callback) => {
     ipcRenderer.on("transcription", callback);
   },
+  onTranscriptionStarted: (callback) => {
+    ipcRenderer.on("transcription-started", callback);
+  },
+  onTranscriptionSegments: (callback) => {
+    ipcRenderer.on("transcription-segments", callback);
+  },
+  onTranscriptionEmpty: (callback) => {
+    ipcRenderer.on("transcription-empty", callback);
+  },
+  onTranscriptionFailed: (callback) => {
+    ipcRenderer.on("transcription-failed", callback);
+  },
   onWorkerReady: (callback) => {
     ipcRenderer.on("worker-ready", callback);
   },
+  onWorkerError: (callback) => {
+    ipcRenderer.on("worker-error", callback);
+  },
+  onToggleRecording: (callback) => {
+    ipcRenderer.on("toggle-recording", callback);
+    return () => ipcRenderer.removeListener("toggle-recording", callback);
+  },
+  microphoneAccess: () => {
+    return ipcRenderer.invoke("microphone-access");
+  },
+  isRecording: () => {
+    return ipcRenderer.invoke("is-recording");
+  },
+  setRecordingState: (isRecording) => {
+    ipcRenderer.send("recording-state", isRecording);
+  },
   Buffer: Buffer,
 });
@@ -0,0 +1,122 @@
+// schema.js
+
+import { defaultHallucinationPatterns } from "./postprocess.js";
+
+export const CONFIG_VERSION = 1;
+
+// CHUNK_LENGTH_S in whisper/worker.py, the overlap is taken from both sides
+// of a chunk so it has to stay under half of it
+const CHUNK_LENGTH_MS = 15000;
+
+// loguru levels, LOG_LEVELS in whisper/worker.py
+export const logLevels = [
+  "TRACE",
+  "DEBUG",
+  "INFO",
+  "SUCCESS",
+  "WARNING",
+  "ERROR",
+  "CRITICAL",
+];
+
+export const defaults = {
+  version: CONFIG_VERSION,
+  hotkey: "CommandOrControl+Shift+D",
+  autoCopy: false,
+  recordingsDir: null,
+  retentionDays: 7,
+  maxRecordingsMb: null,
+  logLevel: "INFO",
+  postTranscriptionAction: "keep",
+  archiveDir: null,
+  retryOnEmpty: false,
+  // chunk_length_s / 6, as recommended for chunked distil-whisper
+  chunkOverlapMs: 2500,
+  temperature: null,
+  initialPrompt: null,
+  stripHallucinations: false,
+  hallucinationPatterns: defaultHallucinationPatterns,
+  replacements: [],
+  numberFormatting: false,
+  locale: "en-US",
+  prependTimestamp: false,
+  timestampFormat: "[YYYY-MM-DD HH:mm]",
+};
+
+// migrations[n] upgrades a config saved at version n to version n + 1
+const migrations = {
+  // configs from before versioning only lack newer fields, which the
+  // defaults fill in
+  0: (saved) => saved,
+};
+
+// bring a saved config up to CONFIG_VERSION, filling in missing fields
+export function migrate(saved) {
+  let version = saved.version ?? 0;
+
+  while (version < CONFIG_VERSION) {
+    console.log(`Migrating config from version ${version}`);
+    saved = { ...migrations[version](saved), version: version + 1 };
+    version++;
+  }
+
+  return { ...defaults, ...saved };
+}
+
+function isValidLocale(locale) {
+  if (typeof locale !== "string") {
+    return false;
+  }
+
+  try {
+    new Intl.NumberFormat(locale);
+    return true;
+  } catch {
+    return false;
+  }
+}
+
+// returns a field -> message map of every invalid field in updates, empty
+// when all of them are valid
+export function validateConfig(updates) {
+  const errors = {};
+
+  if ("locale" in updates && !isValidLocale(updates.locale)) {
+    errors.locale = `Invalid locale: ${updates.locale}`;
+  }
+
+  if ("logLevel" in updates && !logLevels.includes(updates.logLevel)) {
+    errors.logLevel = `Unknown log level: ${updates.logLevel}`;
+  }
+
+  if ("retentionDays" in updates) {
+    const days = updates.retentionDays;
+    if (!Number.isInteger(days) || days < 0) {
+      errors.retentionDays = "Must be a whole number of days, 0 keeps all";
+    }
+  }
+
+  if ("maxRecordingsMb" in updates && updates.maxRecordingsMb !== null) {
+    const maxMb = updates.maxRecordingsMb;
+    if (!Number.isInteger(maxMb) || maxMb <= 0) {
+      errors.maxRecordingsMb = "Must be unset or a positive whole number of MB";
+    }
+  }
+
+  if ("chunkOverlapMs" in updates) {
+    const overlap = updates.chunkOverlapMs;
+    const max = CHUNK_LENGTH_MS / 2;
+    if (!Number.isInteger(overlap) || overlap < 0 || overlap >= max) {
+      errors.chunkOverlapMs = `Must be whole ms from 0 to ${max - 1}`;
+    }
+  }
+
+  if ("temperature" in updates && updates.temperature !== null) {
+    const temperature = updates.temperature;
+    if (!Number.isFinite(temperature) || temperature < 0) {
+      errors.temperature = "Must be unset or a number from 0";
+    }
+  }
+
+  return errors;
+}
@@ -0,0 +1,115 @@
+// schema.test.js
+
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { CONFIG_VERSION, defaults, migrate, validateConfig } from "./schema.js";
+
+test("upgrades an unversioned config and fills in missing fields", () => {
+  const v0 = { hotkey: "Alt+Space", autoCopy: true, retentionDays: 0 };
+  const config = migrate(v0);
+
+  assert.equal(config.version, CONFIG_VERSION);
+  assert.equal(config.hotkey, "Alt+Space");
+  assert.equal(config.autoCopy, true);
+  assert.equal(config.retentionDays, 0);
+  assert.equal(config.logLevel, defaults.logLevel);
+  assert.deepEqual(config.replacements, []);
+  assert.deepEqual(
+    config.hallucinationPatterns,
+    defaults.hallucinationPatterns,
+  );
+});
+
+test("leaves a current config as saved", () => {
+  const saved = { ...defaults, locale: "de-DE" };
+
+  assert.deepEqual(migrate(saved), saved);
+});
+
+test("accepts valid locales", () => {
+  assert.deepEqual(validateConfig({ locale: "en-US" }), {});
+  assert.deepEqual(validateConfig({ locale: "de" }), {});
+});
+
+test("rejects locales Intl can't format with", () => {
+  for (const locale of ["en_US", "", null, 5]) {
+    assert.match(validateConfig({ locale }).locale, /Invalid locale/);
+  }
+});
+
+test("accepts loguru log levels", () => {
+  assert.deepEqual(validateConfig({ logLevel: "DEBUG" }), {});
+});
+
+test("rejects log levels loguru doesn't know", () => {
+  for (const logLevel of ["bogus", "debug", null]) {
+    assert.ok(validateConfig({ logLevel }).logLevel);
+  }
+});
+
+test("accepts zero or positive whole retention days", () => {
+  for (const retentionDays of [0, 1, 30]) {
+    assert.deepEqual(validateConfig({ retentionDays }), {});
+  }
+});
+
+test("rejects retention days that would sweep every recording", () => {
+  for (const retentionDays of [-1, 1.5, "7", null, NaN]) {
+    assert.ok(validateConfig({ retentionDays }).retentionDays);
+  }
+});
+
+test("accepts an unset or positive recordings quota", () => {
+  for (const maxRecordingsMb of [null, 1, 500]) {
+    assert.deepEqual(validateConfig({ maxRecordingsMb }), {});
+  }
+});
+
+test("rejects quotas that would sweep every recording", () => {
+  for (const maxRecordingsMb of [0, -5, 0.5, "abc", "500", undefined]) {
+    assert.ok(validateConfig({ maxRecordingsMb }).maxRecordingsMb);
+  }
+});
+
+test("accepts chunk overlaps under half the chunk length", () => {
+  for (const chunkOverlapMs of [0, 500, 2500, 7499]) {
+    assert.deepEqual(validateConfig({ chunkOverlapMs }), {});
+  }
+});
+
+test("rejects chunk overlaps the pipeline can't use", () => {
+  for (const chunkOverlapMs of [-1, 7500, 250.5, "abc", "500", null]) {
+    assert.ok(validateConfig({ chunkOverlapMs }).chunkOverlapMs);
+  }
+});
+
+test("accepts unset or non-negative temperatures", () => {
+  for (const temperature of [null, 0, 0.2, 1]) {
+    assert.deepEqual(validateConfig({ temperature }), {});
+  }
+});
+
+test("rejects temperatures the worker can't parse", () => {
+  for (const temperature of [undefined, -0.1, NaN, "warm", "0.2"]) {
+    assert.ok(validateConfig({ temperature }).temperature);
+  }
+});
+
+test("reports every invalid field together", () => {
+  const errors = validateConfig({
+    locale: "en_US",
+    chunkOverlapMs: -1,
+    temperature: "warm",
+    autoCopy: true,
+  });
+
+  assert.deepEqual(Object.keys(errors).sort(), [
+    "chunkOverlapMs",
+    "locale",
+    "temperature",
+  ]);
+});
+
+test("ignores fields that aren't being updated", () => {
+  assert.deepEqual(validateConfig({}), {});
+});
@@ -0,0 +1,49 @@
+// subtitles.js
+
+export const subtitleFormats = ["srt", "vtt"];
+
+// reads the worker's [segments] payload, older workers omit words
+export function parseTimestamps(json) {
+  const { text = "", segments = [], words = [] } = JSON.parse(json);
+  return { text, segments, words };
+}
+
+// word-level results carry no segments, so each word becomes its own cue
+export function subtitleCues({ segments, words }) {
+  if (segments.length) {
+    return segments;
+  }
+
+  return words.map(({ word, start, end }) => ({ start, end, text: word }));
+}
+
+function formatTime(seconds, separator) {
+  const ms = Math.round(seconds * 1000);
+  const pad = (n, width = 2) => n.toString().padStart(width, "0");
+
+  const hours = Math.floor(ms / 3600000);
+  const minutes = Math.floor(ms / 60000) % 60;
+  const secs = Math.floor(ms / 1000) % 60;
+
+  const time = [pad(hours), pad(minutes), pad(secs)].join(":");
+  return `${time}${separator}${pad(ms % 1000, 3)}`;
+}
+
+function cues(segments, separator) {
+  return segments.map((segment, i) => {
+    // the pipeline leaves the final end open when audio stops mid-segment
+    const end = segment.end ?? segments[i + 1]?.start ?? segment.start + 2;
+    const start = formatTime(segment.start, separator);
+    return `${start} --> ${formatTime(end, separator)}\n${segment.text}\n`;
+  });
+}
+
+export function formatSrt(segments) {
+  return cues(segments, ",")
+    .map((cue, i) => `${i + 1}\n${cue}`)
+    .join("\n");
+}
+
+export function formatVtt(segments) {
+  return ["WEBVTT\n", ...cues(segments, ".")].join("\n");
+}
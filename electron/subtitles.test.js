@@ -0,0 +1,130 @@
+// subtitles.test.js
+
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import {
+  formatSrt,
+  formatVtt,
+  parseTimestamps,
+  subtitleCues,
+} from "./subtitles.js";
+
+const segments = [
+  { start: 0, end: 1.5, text: "Hello there." },
+  { start: 1.5, end: 3.25, text: "General Kenobi." },
+];
+
+test("formats srt with indices and comma milliseconds", () => {
+  assert.equal(
+    formatSrt(segments),
+    "1\n00:00:00,000 --> 00:00:01,500\nHello there.\n" +
+      "\n" +
+      "2\n00:00:01,500 --> 00:00:03,250\nGeneral Kenobi.\n",
+  );
+});
+
+test("formats vtt with header and dot milliseconds", () => {
+  assert.equal(
+    formatVtt(segments),
+    "WEBVTT\n" +
+      "\n" +
+      "00:00:00.000 --> 00:00:01.500\nHello there.\n" +
+      "\n" +
+      "00:00:01.500 --> 00:00:03.250\nGeneral Kenobi.\n",
+  );
+});
+
+test("formats cues past the hour mark", () => {
+  const long = [{ start: 3599.999, end: 7325.042, text: "Still going." }];
+
+  assert.equal(
+    formatSrt(long),
+    "1\n00:59:59,999 --> 02:02:05,042\nStill going.\n",
+  );
+  assert.equal(
+    formatVtt(long),
+    "WEBVTT\n\n00:59:59.999 --> 02:02:05.042\nStill going.\n",
+  );
+});
+
+test("rounds float timestamps to the nearest millisecond", () => {
+  const cues = [{ start: 0.1 + 0.2, end: 2.0004, text: "Float." }];
+
+  assert.equal(formatSrt(cues), "1\n00:00:00,300 --> 00:00:02,000\nFloat.\n");
+});
+
+test("closes open-ended segments at the next start or after 2s", () => {
+  const open = [
+    { start: 0, end: null, text: "One." },
+    { start: 4, end: null, text: "Two." },
+  ];
+
+  assert.equal(
+    formatSrt(open),
+    "1\n00:00:00,000 --> 00:00:04,000\nOne.\n" +
+      "\n" +
+      "2\n00:00:04,000 --> 00:00:06,000\nTwo.\n",
+  );
+});
+
+test("formats no segments as an empty file", () => {
+  assert.equal(formatSrt([]), "");
+  assert.equal(formatVtt([]), "WEBVTT\n");
+});
+
+test("parses a word-level payload", () => {
+  const payload = JSON.stringify({
+    text: " Hello world",
+    segments: [],
+    words: [
+      { word: "Hello", start: 0.0, end: 0.42 },
+      { word: "world", start: 0.42, end: 0.9 },
+    ],
+  });
+
+  assert.deepEqual(parseTimestamps(payload), {
+    text: " Hello world",
+    segments: [],
+    words: [
+      { word: "Hello", start: 0, end: 0.42 },
+      { word: "world", start: 0.42, end: 0.9 },
+    ],
+  });
+});
+
+test("defaults missing words and segments to empty lists", () => {
+  const payload = JSON.stringify({
+    text: "Hi.",
+    segments: [{ start: 0, end: 1, text: "Hi." }],
+  });
+
+  assert.deepEqual(parseTimestamps(payload).words, []);
+  assert.deepEqual(parseTimestamps('{"text": ""}').segments, []);
+});
+
+test("builds cues from words when there are no segments", () => {
+  const result = parseTimestamps(
+    JSON.stringify({
+      text: " Hello world",
+      segments: [],
+      words: [
+        { word: "Hello", start: 0, end: 0.42 },
+        { word: "world", start: 0.42, end: null },
+      ],
+    }),
+  );
+
+  assert.equal(
+    formatSrt(subtitleCues(result)),
+    "1\n00:00:00,000 --> 00:00:00,420\nHello\n" +
+      "\n" +
+      "2\n00:00:00,420 --> 00:00:02,420\nworld\n",
+  );
+});
+
+test("prefers segments over words for cues", () => {
+  const words = [{ word: "Hello", start: 0, end: 0.42 }];
+
+  assert.deepEqual(subtitleCues({ segments, words }), segments);
+  assert.deepEqual(subtitleCues({ segments: [], words: [] }), []);
+});
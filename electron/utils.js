@@ -1,10 +1,15 @@
 // utils.js
 
-import { spawn } from "child_process";
+import { execFile, spawn } from "child_process";
 import { fileURLToPath } from "url";
+import { promisify } from "util";
 import path from "path";
 import fs from "fs";
 import os from "os";
+import { getConfig, getConfigPath, getLogPath } from "./config.js";
+
+const defaultRecordingsDir = path.join(os.tmpdir(), "dictator");
+const recordingPattern = /^\d{8}-\d{6}\.mp3$/;
 
 export const getFilename = () => {
   return fileURLToPath(import.meta.url);
@@ -14,11 +19,72 @@ export const getDirname = () => {
   return path.dirname(getFilename());
 };
 
-export function createTempAudioFile(audioBuffer, callback) {
-  const tempDir = path.join(os.tmpdir(), "dictator");
-  if (!fs.existsSync(tempDir)) {
-    fs.mkdirSync(tempDir);
+export function ensureWritableDir(dir) {
+  try {
+    fs.mkdirSync(dir, { recursive: true });
+    fs.accessSync(dir, fs.constants.W_OK);
+  } catch {
+    return `Directory is not writable: ${dir}`;
+  }
+
+  return null;
+}
+
+function checkWritable(name, target, isDir) {
+  const dir = isDir ? target : path.dirname(target);
+  let error = ensureWritableDir(dir);
+
+  if (!error && !isDir && fs.existsSync(target)) {
+    try {
+      fs.accessSync(target, fs.constants.W_OK);
+    } catch {
+      error = `File is not writable: ${target}`;
+    }
+  }
+
+  if (error) {
+    // try to restore owner write permission before giving up
+    try {
+      fs.chmodSync(target, fs.statSync(target).mode | 0o200);
+      fs.accessSync(target, fs.constants.W_OK);
+      return { name, path: target, ok: true, repaired: true, error: null };
+    } catch {
+      return { name, path: target, ok: false, repaired: false, error };
+    }
+  }
+
+  return { name, path: target, ok: true, repaired: false, error: null };
+}
+
+export function checkStorage() {
+  const { recordingsDir } = getConfig();
+
+  return [
+    checkWritable("config", path.dirname(getConfigPath()), true),
+    checkWritable("recordings", recordingsDir || defaultRecordingsDir, true),
+    checkWritable("log", getLogPath(), false),
+  ];
+}
+
+export function getRecordingsDir() {
+  const { recordingsDir } = getConfig();
+
+  if (recordingsDir) {
+    const error = ensureWritableDir(recordingsDir);
+    if (!error) {
+      return recordingsDir;
+    }
+    console.warn(`${error}, falling back to ${defaultRecordingsDir}`);
+  }
+
+  if (!fs.existsSync(defaultRecordingsDir)) {
+    fs.mkdirSync(defaultRecordingsDir, { recursive: true });
   }
+  return defaultRecordingsDir;
+}
+
+export function createTempAudioFile(audioBuffer, callback) {
+  const tempDir = getRecordingsDir();
   const date = new Date();
   const fileName = `${date.getDate().toString().padStart(2, "0")}${(
     date.getMonth() + 1
@@ -43,24 +109,424 @@ export function createTempAudioFile(audioBuffer, callback) {
   });
 }
 
-export function deleteAllRecordings() {
-  const tempDir = path.join(os.tmpdir(), "dictator");
-  if (fs.existsSync(tempDir)) {
-    fs.readdir(tempDir, (err, files) => {
-      if (err) throw err;
+const AUDIO_EXTENSIONS = [".wav", ".mp3", ".m4a", ".ogg", ".flac", ".webm"];
+
+export function validateAudioFile(filePath) {
+  if (!AUDIO_EXTENSIONS.includes(path.extname(filePath).toLowerCase())) {
+    return `Unsupported audio format: ${filePath}`;
+  }
+
+  try {
+    fs.accessSync(filePath, fs.constants.R_OK);
+    const stats = fs.statSync(filePath);
+    if (!stats.isFile() || stats.size === 0) {
+      return `Not a readable audio file: ${filePath}`;
+    }
+  } catch {
+    return `Cannot read file: ${filePath}`;
+  }
+
+  return null;
+}
+
+function readWavInfo(filePath) {
+  const fd = fs.openSync(filePath, "r");
+  const header = Buffer.alloc(12);
+  const chunk = Buffer.alloc(8);
+  const info = {};
+
+  try {
+    fs.readSync(fd, header, 0, 12, 0);
+    if (
+      header.toString("ascii", 0, 4) !== "RIFF" ||
+      header.toString("ascii", 8, 12) !== "WAVE"
+    ) {
+      throw new Error(`Corrupt WAV header: ${filePath}`);
+    }
+
+    // walk the chunk list until both fmt and data have been seen
+    let offset = 12;
+    while (fs.readSync(fd, chunk, 0, 8, offset) === 8) {
+      const id = chunk.toString("ascii", 0, 4);
+      const size = chunk.readUInt32LE(4);
+
+      if (id === "fmt ") {
+        const fmt = Buffer.alloc(16);
+        fs.readSync(fd, fmt, 0, 16, offset + 8);
+        info.channels = fmt.readUInt16LE(2);
+        info.sampleRate = fmt.readUInt32LE(4);
+        info.bitsPerSample = fmt.readUInt16LE(14);
+      } else if (id === "data") {
+        info.dataSize = size;
+        break;
+      }
+
+      offset += 8 + size + (size % 2);
+    }
+  } finally {
+    fs.closeSync(fd);
+  }
+
+  if (!info.sampleRate || info.dataSize === undefined) {
+    throw new Error(`Corrupt WAV header: ${filePath}`);
+  }
+
+  const bytesPerSecond =
+    (info.sampleRate * info.channels * info.bitsPerSample) / 8;
+
+  return {
+    sampleRate: info.sampleRate,
+    channels: info.channels,
+    duration: bytesPerSecond ? info.dataSize / bytesPerSecond : null,
+  };
+}
+
+async function probeAudioInfo(filePath) {
+  let stdout;
+  try {
+    ({ stdout } = await promisify(execFile)("ffprobe", [
+      "-v",
+      "error",
+      "-select_streams",
+      "a:0",
+      "-show_entries",
+      "stream=sample_rate,channels:format=duration",
+      "-of",
+      "json",
+      filePath,
+    ]));
+  } catch (err) {
+    // the worker needs ffmpeg too, but don't fail inspection without it
+    if (err.code === "ENOENT") {
+      console.warn(`ffprobe not found, can't probe ${filePath}`);
+      return {};
+    }
+    throw new Error(`Corrupt or unsupported audio file: ${filePath}`);
+  }
+
+  const { streams = [], format = {} } = JSON.parse(stdout);
+  if (!streams.length) {
+    throw new Error(`No audio stream in ${filePath}`);
+  }
+
+  const duration = parseFloat(format.duration);
+  return {
+    sampleRate: parseInt(streams[0].sample_rate, 10) || null,
+    channels: streams[0].channels ?? null,
+    duration: Number.isFinite(duration) ? duration : null,
+  };
+}
+
+export async function inspectAudioFile(filePath) {
+  const error = validateAudioFile(filePath);
+  if (error) {
+    throw new Error(error);
+  }
+
+  const format = path.extname(filePath).slice(1).toLowerCase();
+  const info = {
+    format,
+    size: fs.statSync(filePath).size,
+    sampleRate: null,
+    channels: null,
+    duration: null,
+  };
+
+  // WAV headers are read directly, everything else (including our own
+  // recordings) goes through ffprobe, fields stay null if it's missing
+  Object.assign(
+    info,
+    format === "wav" ? readWavInfo(filePath) : await probeAudioInfo(filePath),
+  );
+
+  return info;
+}
+
+export function resolveRecordingPath(filePath, allowRoot = false) {
+  const root = getRecordingsDir();
+  const resolved = path.resolve(root, filePath);
+  const relative = path.relative(root, resolved);
 
-      for (const file of files) {
-        fs.unlink(path.join(tempDir, file), (err) => {
-          if (err) throw err;
+  if (
+    (!relative && !allowRoot) ||
+    relative.startsWith("..") ||
+    path.isAbsolute(relative)
+  ) {
+    throw new Error(`Path is outside the recordings directory: ${filePath}`);
+  }
+
+  return resolved;
+}
+
+export function listRecordings() {
+  const root = getRecordingsDir();
+  const recordings = [];
+
+  const walk = (dir) => {
+    for (const entry of fs.readdirSync(dir, { withFileTypes: true })) {
+      const entryPath = path.join(dir, entry.name);
+      const extension = path.extname(entry.name).toLowerCase();
+
+      if (entry.isDirectory()) {
+        walk(entryPath);
+      } else if (AUDIO_EXTENSIONS.includes(extension)) {
+        const stats = fs.statSync(entryPath);
+        recordings.push({
+          path: entryPath,
+          name: path.relative(root, entryPath),
+          size: stats.size,
+          modified: stats.mtimeMs,
         });
       }
-    });
+    }
+  };
+
+  walk(root);
+  return recordings.sort((a, b) => b.modified - a.modified);
+}
+
+function relocateRecording(source, destination) {
+  if (!fs.existsSync(source)) {
+    throw new Error(`Recording does not exist: ${source}`);
+  }
+  if (fs.existsSync(destination)) {
+    throw new Error(`A file already exists at ${destination}`);
+  }
+
+  fs.mkdirSync(path.dirname(destination), { recursive: true });
+  fs.renameSync(source, destination);
+  return destination;
+}
+
+export function moveRecording(filePath, subfolder) {
+  const source = resolveRecordingPath(filePath);
+  const folder = resolveRecordingPath(subfolder, true);
+  return relocateRecording(
+    source,
+    resolveRecordingPath(path.join(folder, path.basename(source))),
+  );
+}
+
+export function renameRecording(filePath, newName) {
+  if (!newName || path.basename(newName) !== newName) {
+    throw new Error(`Invalid recording name: ${newName}`);
+  }
+
+  const source = resolveRecordingPath(filePath);
+  const name = path.extname(newName)
+    ? newName
+    : newName + path.extname(source);
+
+  // anything else would drop out of listings and the cleanup sweeps
+  if (!AUDIO_EXTENSIONS.includes(path.extname(name).toLowerCase())) {
+    throw new Error(`Recording names must keep an audio extension: ${name}`);
+  }
+  return relocateRecording(
+    source,
+    resolveRecordingPath(path.join(path.dirname(source), name)),
+  );
+}
+
+export const postTranscriptionActions = ["keep", "delete", "archive"];
+
+function moveFile(source, destination) {
+  try {
+    fs.renameSync(source, destination);
+  } catch (err) {
+    // rename can't cross filesystems, fall back to copying
+    if (err.code !== "EXDEV") throw err;
+    fs.copyFileSync(source, destination);
+    fs.unlinkSync(source);
   }
 }
 
+export function applyPostTranscriptionAction(filePath) {
+  const { postTranscriptionAction, archiveDir } = getConfig();
+
+  // "keep" leaves the file to the retention and size-quota sweeps
+  try {
+    if (postTranscriptionAction === "delete") {
+      fs.unlinkSync(filePath);
+    } else if (postTranscriptionAction === "archive") {
+      const error = archiveDir
+        ? ensureWritableDir(archiveDir)
+        : "No archive directory set";
+      if (error) {
+        console.warn(`${error}, keeping ${filePath}`);
+        return;
+      }
+      moveFile(filePath, path.join(archiveDir, path.basename(filePath)));
+    }
+  } catch (err) {
+    console.error(`Failed to ${postTranscriptionAction} ${filePath}`, err);
+  }
+}
+
+export function cleanupOldRecordings(retentionDays) {
+  // 0 keeps recordings forever, and a bad value must never put the cutoff
+  // in the future and sweep everything
+  if (!Number.isInteger(retentionDays) || retentionDays <= 0) {
+    return;
+  }
+
+  const tempDir = getRecordingsDir();
+  const cutoff = Date.now() - retentionDays * 24 * 60 * 60 * 1000;
+  let deleted = 0;
+  let reclaimed = 0;
+
+  for (const file of fs.readdirSync(tempDir)) {
+    if (!recordingPattern.test(file)) continue;
+
+    const filePath = path.join(tempDir, file);
+    try {
+      const stats = fs.statSync(filePath);
+      if (stats.mtimeMs < cutoff) {
+        fs.unlinkSync(filePath);
+        deleted++;
+        reclaimed += stats.size;
+      }
+    } catch (err) {
+      console.warn(`Skipping ${filePath} during cleanup`, err);
+    }
+  }
+
+  console.log(
+    `Cleaned up ${deleted} recordings older than ${retentionDays} days ` +
+      `(${(reclaimed / 1024 / 1024).toFixed(1)} MB)`,
+  );
+}
+
+export function enforceRecordingsQuota(maxRecordingsMb, keep = []) {
+  // unset means no cap, and a bad value must never sweep everything
+  if (!Number.isFinite(maxRecordingsMb) || maxRecordingsMb <= 0) {
+    return;
+  }
+
+  const tempDir = getRecordingsDir();
+  const limit = maxRecordingsMb * 1024 * 1024;
+  const recordings = [];
+  let total = 0;
+
+  for (const file of fs.readdirSync(tempDir)) {
+    if (!recordingPattern.test(file)) continue;
+
+    const filePath = path.join(tempDir, file);
+    try {
+      const stats = fs.statSync(filePath);
+      recordings.push({ filePath, size: stats.size, mtimeMs: stats.mtimeMs });
+      total += stats.size;
+    } catch (err) {
+      console.warn(`Skipping ${filePath} during quota check`, err);
+    }
+  }
+
+  if (total <= limit) {
+    return;
+  }
+
+  const protectedPaths = new Set(keep.map((kept) => path.resolve(kept)));
+  let deleted = 0;
+  let reclaimed = 0;
+
+  // oldest first, never touching recordings still waiting on the worker
+  recordings.sort((a, b) => a.mtimeMs - b.mtimeMs);
+  for (const { filePath, size } of recordings) {
+    if (total <= limit) break;
+    if (protectedPaths.has(path.resolve(filePath))) continue;
+
+    try {
+      fs.unlinkSync(filePath);
+      total -= size;
+      deleted++;
+      reclaimed += size;
+    } catch (err) {
+      console.warn(`Failed to delete ${filePath} during quota check`, err);
+    }
+  }
+
+  console.log(
+    `Deleted ${deleted} recordings to stay under ${maxRecordingsMb} MB ` +
+      `(${(reclaimed / 1024 / 1024).toFixed(1)} MB reclaimed)`,
+  );
+}
+
+export function tailFile(filePath, lines) {
+  if (!fs.existsSync(filePath)) {
+    return [];
+  }
+
+  // read backwards in blocks so huge logs aren't loaded whole
+  const blockSize = 64 * 1024;
+  const fd = fs.openSync(filePath, "r");
+  let position = fs.fstatSync(fd).size;
+  let data = Buffer.alloc(0);
+  let newlines = 0;
+
+  try {
+    while (position > 0 && newlines <= lines) {
+      const length = Math.min(blockSize, position);
+      const block = Buffer.alloc(length);
+      position -= length;
+      fs.readSync(fd, block, 0, length, position);
+      newlines += block.filter((byte) => byte === 0x0a).length;
+      data = Buffer.concat([block, data]);
+    }
+  } finally {
+    fs.closeSync(fd);
+  }
+
+  // decode once at the end so characters spanning blocks stay intact
+  return data.toString("utf-8").trimEnd().split("\n").slice(-lines);
+}
+
+export function followFile(filePath, onLines) {
+  const listener = (curr, prev) => {
+    // the file shrinks when loguru rotates it, start over from the top
+    const start = curr.size < prev.size ? 0 : prev.size;
+    if (curr.size === start) return;
+
+    const stream = fs.createReadStream(filePath, {
+      start,
+      end: curr.size - 1,
+      encoding: "utf-8",
+    });
+    let text = "";
+    stream.on("data", (chunk) => (text += chunk));
+    stream.on("end", () => onLines(text.trimEnd().split("\n")));
+  };
+
+  fs.watchFile(filePath, { interval: 1000 }, listener);
+  return () => fs.unwatchFile(filePath, listener);
+}
+
+// config fields the worker reads at spawn, changing them needs a restart
+export const workerConfigKeys = [
+  "retryOnEmpty",
+  "chunkOverlapMs",
+  "temperature",
+  "initialPrompt",
+];
+
 export function spawnWorker() {
   const workerPath = path.join(getDirname(), "whisper", "worker.py");
-  const worker = spawn(workerPath, [], { shell: true });
+  const config = getConfig();
+  const env = {
+    ...process.env,
+    DICTATOR_LOG_FILE: getLogPath(),
+    DICTATOR_LOG_LEVEL: config.logLevel,
+    DICTATOR_RETRY_ON_EMPTY: config.retryOnEmpty ? "1" : "0",
+    DICTATOR_CHUNK_OVERLAP_MS: config.chunkOverlapMs.toString(),
+  };
+
+  // unset options are left out so the model's own defaults apply
+  if (config.temperature !== null) {
+    env.DICTATOR_TEMPERATURE = config.temperature.toString();
+  }
+  if (config.initialPrompt) {
+    env.DICTATOR_INITIAL_PROMPT = config.initialPrompt;
+  }
+
+  const worker = spawn(workerPath, [], { shell: true, env });
   return worker;
 }
 
@@ -5,22 +5,52 @@
 
 from typing import Callable, Optional
 from pathlib import Path
+import json
 import time
 import sys
+import os
 
 from transformers import AutoModelForSpeechSeq2Seq, AutoProcessor, pipeline
+from transformers.pipelines.audio_utils import ffmpeg_read
 from loguru import logger
 import torch
 
-logger.add(
-    "worker.log",
-    format="{time} {level} {message}",
-    level="INFO",
-    rotation="1 week",
-    compression="zip",
-)
-
 MODEL = "distil-whisper/distil-small.en"
+RETRY_ON_EMPTY = os.environ.get("DICTATOR_RETRY_ON_EMPTY") == "1"
+LOG_FILE = os.environ.get("DICTATOR_LOG_FILE", "worker.log")
+TEMPERATURE = os.environ.get("DICTATOR_TEMPERATURE")
+INITIAL_PROMPT = os.environ.get("DICTATOR_INITIAL_PROMPT")
+CHUNK_LENGTH_S = 15
+# per side of each chunk, the pipeline needs it under half the chunk length
+CHUNK_OVERLAP_S = float(os.environ.get("DICTATOR_CHUNK_OVERLAP_MS", "2500")) / 1000
+LOG_LEVELS = ("TRACE", "DEBUG", "INFO", "SUCCESS", "WARNING", "ERROR", "CRITICAL")
+
+log_handler = None
+
+
+def set_log_level(level: str) -> None:
+    """Replace the log file sink with one at the given level."""
+
+    global log_handler
+    if log_handler is not None:
+        logger.remove(log_handler)
+
+    log_handler = logger.add(
+        LOG_FILE,
+        format="{time} {level} {message}",
+        level=level,
+        rotation="1 week",
+        compression="zip",
+    )
+
+
+# an unknown level would make loguru raise before the worker is ready
+LOG_LEVEL = os.environ.get("DICTATOR_LOG_LEVEL", "INFO").upper()
+if LOG_LEVEL in LOG_LEVELS:
+    set_log_level(LOG_LEVEL)
+else:
+    set_log_level("INFO")
+    logger.warning(f"Unknown log level {LOG_LEVEL}, using INFO")
 
 
 def check_cuda() -> bool:
@@ -36,9 +66,6 @@ def check_cuda() -> bool:
 def load_model() -> Optional[Callable]:
     """Load model onto GPU."""
 
-    # TODO: rn this only handles short (<30s) clips efficiently
-    # TODO: implement the chunking strategy in distil-whisper README
-
     try:
         device = "cuda:0" if check_cuda() else "cpu"
         torch_dtype = torch.float16 if check_cuda() else torch.float32
@@ -56,6 +83,10 @@ def load_model() -> Optional[Callable]:
             tokenizer=processor.tokenizer,
             feature_extractor=processor.feature_extractor,
             max_new_tokens=128,
+            # long clips are split into overlapping chunks, the pipeline
+            # merges the overlap so boundary words aren't dropped or repeated
+            chunk_length_s=CHUNK_LENGTH_S,
+            stride_length_s=CHUNK_OVERLAP_S,
             torch_dtype=torch_dtype,
             device=device,
         )
@@ -65,17 +96,83 @@ def load_model() -> Optional[Callable]:
         return None
 
 
+def generate_kwargs(pipe) -> dict:
+    """Build decoding options from the configured temperature and prompt."""
+
+    kwargs = {}
+
+    if TEMPERATURE is not None:
+        kwargs["temperature"] = float(TEMPERATURE)
+        kwargs["do_sample"] = float(TEMPERATURE) > 0
+
+    if INITIAL_PROMPT:
+        prompt_ids = pipe.tokenizer.get_prompt_ids(INITIAL_PROMPT, return_tensors="pt")
+        kwargs["prompt_ids"] = prompt_ids.to(pipe.device)
+
+    return kwargs
+
+
+def load_audio(pipe, audiofile) -> dict:
+    """Decode audio file at the rate the model expects."""
+
+    sampling_rate = pipe.feature_extractor.sampling_rate
+    with open(audiofile, "rb") as f:
+        audio = ffmpeg_read(f.read(), sampling_rate)
+    return {"raw": audio, "sampling_rate": sampling_rate}
+
+
 def transcribe(pipe, audiofile):
-    """Transcribe audio file."""
+    """Transcribe audio file, returning the text, elapsed and audio seconds."""
 
     tic = time.time()
     try:
-        result = pipe(audiofile)
+        # decoded up front so the audio length is known, the pipeline pops
+        # keys off its input so each call gets a copy
+        audio = load_audio(pipe, audiofile)
+        seconds = len(audio["raw"]) / audio["sampling_rate"]
+
+        kwargs = generate_kwargs(pipe)
+        result = pipe(dict(audio), generate_kwargs=kwargs)
+
+        # greedy decoding occasionally yields nothing for real speech
+        if RETRY_ON_EMPTY and not result["text"].strip():
+            logger.warning(f"Empty transcript for {audiofile}, retrying with beams")
+            result = pipe(dict(audio), generate_kwargs={**kwargs, "num_beams": 5})
+
         torch.cuda.empty_cache()
-        return result["text"], time.time() - tic
+        return result["text"], time.time() - tic, seconds
     except Exception as e:
         logger.error(f"Error during transcription: {e}")
-        return "[error] Transcription failed.", 0.0
+        return None, 0.0, 0.0
+
+
+def transcribe_segments(pipe, audiofile, words=False) -> Optional[dict]:
+    """Transcribe audio file, keeping per-segment or per-word timestamps."""
+
+    try:
+        result = pipe(
+            audiofile,
+            generate_kwargs=generate_kwargs(pipe),
+            return_timestamps="word" if words else True,
+        )
+        torch.cuda.empty_cache()
+    except Exception as e:
+        logger.error(f"Error during transcription: {e}")
+        return None
+
+    # the pipeline returns either segment or word chunks, never both
+    chunks = []
+    for chunk in result.get("chunks", []):
+        start, end = chunk["timestamp"]
+        chunks.append({"start": start, "end": end, "text": chunk["text"].strip()})
+
+    if words:
+        timings = [
+            {"word": c["text"], "start": c["start"], "end": c["end"]} for c in chunks
+        ]
+        return {"text": result["text"], "segments": [], "words": timings}
+
+    return {"text": result["text"], "segments": chunks, "words": []}
 
 
 def print_(*args, **kwargs):
@@ -84,33 +181,78 @@ def print_(*args, **kwargs):
     sys.stdout.flush()
 
 
-def print_transcript(transcript, duration):
+def print_transcript(job_id, transcript, duration, seconds):
     """Print transcript and duration"""
-    print_(f"[transcript] {transcript}")
+    if transcript is None:
+        print_(f"[failed] {job_id} Transcription failed.")
+        return
+
+    print_(f"[timing] {job_id} {duration:.3f} {seconds:.3f}")
+    if transcript.strip():
+        print_(f"[transcript] {job_id} {transcript}")
+    else:
+        print_(f"[empty] {job_id}")
 
 
 if __name__ == "__main__":
+    # transcripts and file paths may be non-ascii regardless of the platform locale
+    sys.stdin.reconfigure(encoding="utf-8")
+    sys.stdout.reconfigure(encoding="utf-8")
+
     pipe = load_model()
 
+    # exit instead of claiming to be ready, main restarts the worker
+    if pipe is None:
+        print_("[error] Model failed to load.")
+        sys.exit(1)
+
     print_("[ready]")
 
     try:
         for line in sys.stdin:
-            if r"\transcribe" in line:
-                cmd, audiofile = line.split()
+            # compare the command token exactly, paths like C:\transcribed\a.wav
+            # contain command names too
+            cmd, *args = line.strip().split(maxsplit=2) or [""]
+
+            if cmd == r"\transcribe" and len(args) == 2:
+                job_id, audiofile = args
 
                 if Path(audiofile).exists():
                     logger.info(f"Transcribing {audiofile}")
                     tic = time.time()
 
-                    transcript, duration = transcribe(pipe, audiofile)
-                    print_transcript(transcript, duration)
+                    transcript, duration, seconds = transcribe(pipe, audiofile)
+                    print_transcript(job_id, transcript, duration, seconds)
 
                     logger.info(f"Transcribed {audiofile} in {time.time() - tic:.2f}s")
                 else:
-                    print_("[error] File does not exist.")
+                    print_(f"[failed] {job_id} File does not exist.")
+
+            elif cmd in (r"\segments", r"\words") and len(args) == 2:
+                job_id, audiofile = args
+                words = cmd == r"\words"
+
+                if Path(audiofile).exists():
+                    logger.info(f"Transcribing {audiofile} with timestamps")
+                    result = transcribe_segments(pipe, audiofile, words=words)
+
+                    if result is None:
+                        print_(f"[failed] {job_id} Transcription failed.")
+                    else:
+                        print_(f"[segments] {job_id} {json.dumps(result)}")
+                else:
+                    print_(f"[failed] {job_id} File does not exist.")
+
+            elif cmd == r"\loglevel" and len(args) == 1:
+                level = args[0].upper()
+
+                if level in LOG_LEVELS:
+                    set_log_level(level)
+                    print_(f"[loglevel] {level}")
+                else:
+                    print_("[error] Unknown log level.")
 
-            elif r"\exit" in line:
+            elif cmd == r"\exit":
                 del pipe
                 break
 
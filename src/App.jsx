@@ -1,6 +1,6 @@
 // src/App.jsx
 
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import { MicButton } from "./components/mic-button";
 import { RecordingIndicator } from "./components/recording-indicator";
 import { StatusMessage } from "./components/status-message";
@@ -13,6 +13,16 @@ export default function App() {
     setIsRecording(!isRecording);
   };
 
+  useEffect(() => {
+    return window.nodeAPI?.onToggleRecording(() =>
+      setIsRecording((recording) => !recording),
+    );
+  }, []);
+
+  useEffect(() => {
+    window.nodeAPI?.setRecordingState(isRecording);
+  }, [isRecording]);
+
   return (
     <div className="App">
       <div className="p-4 max-w-sm mx-auto bg-white flex items-center space-x-2">